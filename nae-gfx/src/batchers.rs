@@ -6,8 +6,8 @@ type VERTICES = Vec<f32>;
 type INDICES = Vec<u32>;
 
 use crate::{
-    matrix4_mul_vector4, DrawData, Graphics, IndexBuffer, Matrix4, Pipeline, Shader, Uniform,
-    VertexAttr, VertexBuffer, VertexFormat,
+    matrix4_mul_vector4, DrawData, Graphics, IndexBuffer, Matrix4, Pipeline, Shader, Texture,
+    Uniform, VertexAttr, VertexBuffer, VertexFormat,
 };
 use nae_core::{
     log, BaseGfx, BasePipeline, BlendMode, Color, DrawUsage, GraphicsAPI, PipelineOptions,
@@ -41,23 +41,50 @@ fn batch_vertices(offset: usize) -> usize {
     size
 }
 
+/// Default depth of the streaming buffer ring. A ring lets the CPU fill the
+/// next slot before the GPU has finished reading the previous one.
+pub(crate) const DEFAULT_RING_DEPTH: usize = 3;
+
+/// Floats per incoming image vertex: position (xyz) followed by uv.
+const IMAGE_VERTEX_IN: usize = 5;
+
+/// A draw buffered for the deferred sort-and-merge pass. Owns its geometry and
+/// the texture it samples so the command list can outlive the caller's borrow
+/// until `flush_all`.
+struct ImageCommand {
+    blend: Option<BlendMode>,
+    matrix: Matrix4,
+    color: Color,
+    alpha: f32,
+    texture: Texture,
+    vertices: VERTICES,
+    indices: INDICES,
+}
+
 /// Image batcher
 pub(crate) struct ImageBatcher {
     pipeline: Pipeline,
-    vbo: VertexBuffer,
-    ibo: IndexBuffer,
+    vbos: Vec<VertexBuffer>,
+    ibos: Vec<IndexBuffer>,
+    ring: usize,
     vertices: VERTICES,
     indices: INDICES,
+    quad_indices: INDICES,
+    quad_mode: bool,
+    deferred: bool,
+    commands: Vec<ImageCommand>,
     matrix_loc: Uniform,
     texture_matrix_loc: Uniform,
     texture_loc: Uniform,
+    texture: Option<Texture>,
     index: usize,
+    vcount: usize,
     max_vertices: usize,
     batch_size: usize,
 }
 
 impl ImageBatcher {
-    pub fn new(gfx: &mut Graphics) -> Result<Self, String> {
+    pub fn new(gfx: &mut Graphics, ring_depth: usize) -> Result<Self, String> {
         let shader = Shader::new(gfx, Shader::IMAGE_VERTEX, Shader::IMAGE_FRAG)?;
         let pipeline = Pipeline::new(
             gfx,
@@ -72,55 +99,352 @@ impl ImageBatcher {
         let texture_matrix_loc = pipeline.uniform_location("u_tex_matrix");
         let texture_loc = pipeline.uniform_location("u_texture");
 
-        let vertex_buffer = VertexBuffer::new(
-            gfx,
-            &[
-                VertexAttr::new(0, VertexFormat::Float3),
-                VertexAttr::new(1, VertexFormat::Float4),
-                VertexAttr::new(2, VertexFormat::Float2),
-            ],
-            DrawUsage::Dynamic,
-        )?;
-
-        let index_buffer = IndexBuffer::new(gfx, DrawUsage::Dynamic)?;
+        let ring_depth = ring_depth.max(1);
+        let mut vbos = Vec::with_capacity(ring_depth);
+        let mut ibos = Vec::with_capacity(ring_depth);
+        for _ in 0..ring_depth {
+            vbos.push(VertexBuffer::new(
+                gfx,
+                &[
+                    VertexAttr::new(0, VertexFormat::Float3),
+                    VertexAttr::new(1, VertexFormat::Float4),
+                    VertexAttr::new(2, VertexFormat::Float2),
+                ],
+                DrawUsage::Dynamic,
+            )?);
+            ibos.push(IndexBuffer::new(gfx, DrawUsage::Dynamic)?);
+        }
 
         let max_vertices = max_vertices(gfx);
-        let batch_size = batch_vertices(vertex_buffer.offset());
+        let batch_size = batch_vertices(vbos[0].offset());
 
         let vertices = vec![0.0; batch_size];
-        let indices = vec![0; batch_size / vertex_buffer.offset()];
+        let indices = vec![0; batch_size / vbos[0].offset()];
+        let mut quad_indices = vec![0; indices.len()];
+        fill_quad_indices(&mut quad_indices);
 
         Ok(Self {
             pipeline,
-            vbo: vertex_buffer,
-            ibo: index_buffer,
+            vbos,
+            ibos,
+            ring: 0,
             matrix_loc,
-            texture_loc,
             texture_matrix_loc,
+            texture_loc,
+            texture: None,
             vertices,
             indices,
+            quad_indices,
+            quad_mode: false,
+            deferred: false,
+            commands: Vec::new(),
             index: 0,
+            vcount: 0,
             max_vertices,
             batch_size,
         })
     }
+
+    /// Enable deferred mode. While enabled `push_data` only buffers draws;
+    /// `flush_all` then groups them by blend mode so interleaved blends collapse
+    /// into one run per blend mode.
+    ///
+    /// Texture is not part of the grouping key, the same blend-only key the
+    /// color batcher uses: within a blend group draws keep their submitted order,
+    /// so painter's order holds for overlapping translucent sprites, and each
+    /// texture change inside a group flushes the current run.
+    pub fn set_deferred(&mut self, enabled: bool) {
+        self.deferred = enabled;
+    }
+
+    /// Enable the implicit quad index buffer. Sprites and text are overwhelmingly
+    /// quads, so in quad mode the index count and vertex count advance
+    /// independently (6 indices per 4 vertices) and `flush` binds the pre-built
+    /// `[0,1,2,2,3,0]` pattern instead of written indices.
+    pub fn set_quad_mode(&mut self, gfx: &mut Graphics, projection: &Matrix4, enabled: bool) {
+        if self.quad_mode != enabled {
+            self.flush(gfx, projection);
+            self.quad_mode = enabled;
+        }
+    }
+
+    pub fn push_data(&mut self, gfx: &mut Graphics, texture: &Texture, data: DrawData) {
+        // In deferred mode the draw is buffered and replayed later by flush_all.
+        if self.deferred {
+            self.commands.push(ImageCommand {
+                blend: data.blend,
+                matrix: *data.matrix,
+                color: data.color,
+                alpha: data.alpha,
+                texture: texture.clone(),
+                vertices: data.vertices.to_vec(),
+                indices: data.indices.to_vec(),
+            });
+            return;
+        }
+
+        // Flush if we reach the end of this batch
+        let next_index = self.index + data.indices.len();
+        if next_index >= self.indices.len() {
+            self.flush(gfx, data.projection);
+        }
+
+        // Flush if we change the blend mode
+        if self.pipeline.options.color_blend != data.blend {
+            self.flush(gfx, data.projection);
+            self.pipeline.options.color_blend = data.blend;
+        }
+
+        // A batch draws a single texture, so flush when the bound texture changes.
+        // Coalescing several textures into one draw would need per-slot uniform
+        // locations (e.g. u_texture[N]) bound by an array-sampler shader; this
+        // crate only has a single `u_texture` location, so that is left to a
+        // future backend change rather than faked with a loop that rebinds every
+        // texture onto the same uniform.
+        if self.texture.as_ref() != Some(texture) {
+            self.flush(gfx, data.projection);
+            self.texture = Some(texture.clone());
+        }
+
+        self.push_vertices(
+            data.indices,
+            data.vertices,
+            &data.color,
+            data.matrix,
+            data.alpha,
+        );
+    }
+
+    /// Replay the buffered command list. Draws are stably grouped by blend mode
+    /// so equal-blend draws keep submission order, then each run is emitted with
+    /// a single blend change. A batch still binds one texture, so a texture
+    /// change within a blend group flushes the current run.
+    pub fn flush_all(&mut self, gfx: &mut Graphics, projection: &Matrix4) {
+        if self.commands.is_empty() {
+            return;
+        }
+
+        let commands = std::mem::take(&mut self.commands);
+        let blends: Vec<Option<BlendMode>> = commands.iter().map(|c| c.blend).collect();
+        let order = state_sorted_order(&blends);
+
+        for &i in &order {
+            let cmd = &commands[i];
+
+            // Flush on a blend change so each run draws under one state.
+            if self.pipeline.options.color_blend != cmd.blend {
+                self.flush(gfx, projection);
+                self.pipeline.options.color_blend = cmd.blend;
+            }
+
+            // A batch draws a single texture, so flush when it changes.
+            if self.texture.as_ref() != Some(&cmd.texture) {
+                self.flush(gfx, projection);
+                self.texture = Some(cmd.texture.clone());
+            }
+
+            // Flush when this draw would overflow the current batch.
+            if self.index + cmd.indices.len() >= self.indices.len() {
+                self.flush(gfx, projection);
+            }
+
+            self.push_vertices(
+                &cmd.indices,
+                &cmd.vertices,
+                &cmd.color,
+                &cmd.matrix,
+                cmd.alpha,
+            );
+        }
+
+        self.flush(gfx, projection);
+    }
+
+    fn push_vertices(
+        &mut self,
+        indices: &[u32],
+        vertices: &[f32],
+        color: &Color,
+        matrix: &Matrix4,
+        alpha: f32,
+    ) {
+        // Out of quad mode indices are written explicitly, remapped against the
+        // running vertex base. In quad mode the pre-built buffer already encodes
+        // them, so only the counts advance.
+        if !self.quad_mode {
+            for (i, index) in indices.iter().enumerate() {
+                self.indices[self.index + i] = self.vcount as u32 + *index;
+            }
+        }
+
+        let offset = self.vbos[0].offset();
+        let [r, g, b, a] = color.to_rgba();
+        let mut index_offset = self.vcount * offset;
+
+        let mut written = 0;
+        for chunk in vertices.chunks_exact(IMAGE_VERTEX_IN) {
+            let [x, y, z, _] = matrix4_mul_vector4(matrix, &[chunk[0], chunk[1], chunk[2], 1.0]);
+
+            self.vertices[index_offset + 0] = x;
+            self.vertices[index_offset + 1] = y;
+            self.vertices[index_offset + 2] = z;
+            self.vertices[index_offset + 3] = r;
+            self.vertices[index_offset + 4] = g;
+            self.vertices[index_offset + 5] = b;
+            self.vertices[index_offset + 6] = a * alpha;
+            self.vertices[index_offset + 7] = chunk[3];
+            self.vertices[index_offset + 8] = chunk[4];
+
+            index_offset += offset;
+            written += 1;
+        }
+
+        self.vcount += written;
+        self.index += indices.len();
+    }
+
+    pub fn flush(&mut self, gfx: &mut Graphics, projection: &Matrix4) {
+        if self.index == 0 {
+            return;
+        }
+
+        let indices = if self.quad_mode {
+            &self.quad_indices
+        } else {
+            &self.indices
+        };
+
+        gfx.set_pipeline(&self.pipeline);
+        gfx.bind_vertex_buffer(&self.vbos[self.ring], &self.vertices);
+        gfx.bind_index_buffer(&self.ibos[self.ring], indices);
+        gfx.bind_uniform(&self.matrix_loc, projection);
+        if let Some(texture) = &self.texture {
+            gfx.bind_texture(&self.texture_loc, texture);
+        }
+        gfx.draw(0, self.index as i32);
+
+        self.ring = (self.ring + 1) % self.vbos.len();
+        self.index = 0;
+        self.vcount = 0;
+    }
+}
+
+/// Fill `indices` with the repeating quad pattern `[0,1,2,2,3,0]` offset by 4
+/// per quad, so a pre-built index buffer can be reused without touching the
+/// per-sprite hot path.
+fn fill_quad_indices(indices: &mut [u32]) {
+    const PATTERN: [u32; 6] = [0, 1, 2, 2, 3, 0];
+    for (quad, chunk) in indices.chunks_exact_mut(6).enumerate() {
+        let base = quad as u32 * 4;
+        for (slot, offset) in chunk.iter_mut().zip(PATTERN.iter()) {
+            *slot = base + *offset;
+        }
+    }
+}
+
+/// A draw buffered for the deferred sort-and-merge pass. Owns its geometry so
+/// the command list can outlive the caller's borrow until `flush_all`.
+struct ColorCommand {
+    blend: Option<BlendMode>,
+    matrix: Matrix4,
+    color: Color,
+    alpha: f32,
+    vertices: VERTICES,
+    indices: INDICES,
+}
+
+/// Stable ordering of buffered draws by pipeline state. The pipeline is fixed
+/// for a given batcher, so the only varying key is the blend mode. Draws are
+/// grouped by the first-appearance order of their blend: this merges same-state
+/// draws without depending on `BlendMode` being `Ord`, and the stable sort
+/// keeps submission order within a group so painter's order is preserved.
+fn state_sorted_order(blends: &[Option<BlendMode>]) -> Vec<usize> {
+    let mut distinct: Vec<Option<BlendMode>> = Vec::new();
+    let mut keys = Vec::with_capacity(blends.len());
+    for blend in blends {
+        let key = match distinct.iter().position(|d| d == blend) {
+            Some(k) => k,
+            None => {
+                distinct.push(*blend);
+                distinct.len() - 1
+            }
+        };
+        keys.push(key);
+    }
+
+    let mut order: Vec<usize> = (0..blends.len()).collect();
+    order.sort_by_key(|&i| keys[i]);
+    order
+}
+
+/// Verify that `indices` only address vertices actually present in a
+/// `vertices_len`-float buffer holding `offset` floats per vertex, so a
+/// malformed `DrawData` is rejected before `split_and_flush` starts slicing
+/// out of bounds.
+fn validate_indices_in_bounds(
+    indices: &[u32],
+    vertices_len: usize,
+    offset: usize,
+) -> Result<(), String> {
+    let vertices_end = indices
+        .iter()
+        .copied()
+        .max()
+        .map(|m| m as usize + 1)
+        .unwrap_or(0);
+    let needed = vertices_end
+        .checked_mul(offset)
+        .ok_or_else(|| "ColorBatcher -> vertex count overflow".to_string())?;
+    if needed > vertices_len {
+        return Err(format!(
+            "ColorBatcher -> indices reference {} vertices but only {} were provided",
+            vertices_end,
+            vertices_len / offset
+        ));
+    }
+    Ok(())
+}
+
+/// Verify that the vertex-id range a sub-batch `span` references fits within
+/// `vertex_capacity`, returning that range's `(min, max)` so the caller can
+/// remap indices and slice the source vertices against it.
+fn validate_span_fits_capacity(
+    span: &[u32],
+    vertex_capacity: usize,
+) -> Result<(usize, usize), String> {
+    let min = *span.iter().min().unwrap() as usize;
+    let max = *span.iter().max().unwrap() as usize;
+    let vertices_used = max - min + 1;
+    if vertices_used > vertex_capacity {
+        return Err(format!(
+            "ColorBatcher -> sub-batch references {} vertices, exceeds capacity {}",
+            vertices_used, vertex_capacity
+        ));
+    }
+    Ok((min, max))
 }
 
 /// Color batcher
 pub(crate) struct ColorBatcher {
     pipeline: Pipeline,
-    vbo: VertexBuffer,
-    ibo: IndexBuffer,
+    vbos: Vec<VertexBuffer>,
+    ibos: Vec<IndexBuffer>,
+    ring: usize,
     vertices: VERTICES,
     indices: INDICES,
+    quad_indices: INDICES,
+    quad_mode: bool,
+    deferred: bool,
+    commands: Vec<ColorCommand>,
     matrix_loc: Uniform,
     index: usize,
+    vcount: usize,
     max_vertices: usize,
     batch_size: usize,
 }
 
 impl ColorBatcher {
-    pub fn new(gfx: &mut Graphics) -> Result<Self, String> {
+    pub fn new(gfx: &mut Graphics, ring_depth: usize) -> Result<Self, String> {
         let shader = Shader::new(gfx, Shader::COLOR_VERTEX, Shader::COLOR_FRAG)?;
         let pipeline = Pipeline::new(
             gfx,
@@ -133,36 +457,69 @@ impl ColorBatcher {
 
         let matrix_loc = pipeline.uniform_location("u_matrix");
 
-        let vertex_buffer = VertexBuffer::new(
-            &gfx,
-            &[
-                VertexAttr::new(0, VertexFormat::Float3),
-                VertexAttr::new(1, VertexFormat::Float4),
-            ],
-            DrawUsage::Dynamic,
-        )?;
-
-        let index_buffer = IndexBuffer::new(gfx, DrawUsage::Dynamic)?;
+        let ring_depth = ring_depth.max(1);
+        let mut vbos = Vec::with_capacity(ring_depth);
+        let mut ibos = Vec::with_capacity(ring_depth);
+        for _ in 0..ring_depth {
+            vbos.push(VertexBuffer::new(
+                &gfx,
+                &[
+                    VertexAttr::new(0, VertexFormat::Float3),
+                    VertexAttr::new(1, VertexFormat::Float4),
+                ],
+                DrawUsage::Dynamic,
+            )?);
+            ibos.push(IndexBuffer::new(gfx, DrawUsage::Dynamic)?);
+        }
 
         let max_vertices = max_vertices(gfx);
-        let batch_size = batch_vertices(vertex_buffer.offset());
+        let batch_size = batch_vertices(vbos[0].offset());
 
         let vertices = vec![0.0; batch_size];
-        let indices = vec![0; batch_size / vertex_buffer.offset()];
+        let indices = vec![0; batch_size / vbos[0].offset()];
+        let mut quad_indices = vec![0; indices.len()];
+        fill_quad_indices(&mut quad_indices);
 
         Ok(Self {
             pipeline,
-            vbo: vertex_buffer,
-            ibo: index_buffer,
+            vbos,
+            ibos,
+            ring: 0,
             matrix_loc,
             vertices,
             indices,
+            quad_indices,
+            quad_mode: false,
+            deferred: false,
+            commands: Vec::new(),
             index: 0,
+            vcount: 0,
             max_vertices,
             batch_size,
         })
     }
 
+    /// Enable deferred mode. While enabled `push_data` only buffers draws;
+    /// `flush_all` then sorts them by pipeline state and emits one draw per
+    /// contiguous run, so interleaved blend modes no longer force a draw each.
+    pub fn set_deferred(&mut self, enabled: bool) {
+        self.deferred = enabled;
+    }
+
+    /// Enable the implicit quad index buffer. In quad mode the index count and
+    /// vertex count advance independently (6 indices per 4 vertices) and `flush`
+    /// binds the pre-built `[0,1,2,2,3,0]` pattern instead of written indices.
+    /// Off by default and left for calling code to enable: this batcher also
+    /// draws non-quad geometry, and this crate has no draw call site of its own
+    /// to decide when every submitted shape is a quad, so wiring this up is
+    /// follow-up work for whatever constructs and drives the batcher.
+    pub fn set_quad_mode(&mut self, gfx: &mut Graphics, projection: &Matrix4, enabled: bool) {
+        if self.quad_mode != enabled {
+            self.flush(gfx, projection);
+            self.quad_mode = enabled;
+        }
+    }
+
     fn check_batch_size(&mut self, gfx: &mut Graphics, data: &DrawData) {
         let next_size = self.vertices.len() + self.batch_size;
         let can_be_bigger = next_size < self.max_vertices;
@@ -172,7 +529,7 @@ impl ColorBatcher {
             if is_bigger || is_more {
                 self.flush(gfx, data.projection);
 
-                let index_next_size = next_size / self.vbo.offset();
+                let index_next_size = next_size / self.vbos[0].offset();
                 log::debug!(
                     "ColorBatcher -> Increasing vertex_buffer to {} and index_buffer to {}",
                     next_size,
@@ -181,11 +538,26 @@ impl ColorBatcher {
 
                 self.vertices.resize(next_size, 0.0);
                 self.indices.resize(index_next_size, 0);
+                self.quad_indices.resize(index_next_size, 0);
+                fill_quad_indices(&mut self.quad_indices);
             }
         }
     }
 
-    pub fn push_data(&mut self, gfx: &mut Graphics, data: DrawData) {
+    pub fn push_data(&mut self, gfx: &mut Graphics, data: DrawData) -> Result<(), String> {
+        // In deferred mode the draw is buffered and replayed later by flush_all.
+        if self.deferred {
+            self.commands.push(ColorCommand {
+                blend: data.blend,
+                matrix: *data.matrix,
+                color: data.color,
+                alpha: data.alpha,
+                vertices: data.vertices.to_vec(),
+                indices: data.indices.to_vec(),
+            });
+            return Ok(());
+        }
+
         self.check_batch_size(gfx, &data);
 
         // Check if the batch is bigger than the max_vertices allowed and split it
@@ -212,32 +584,136 @@ impl ColorBatcher {
             &data.color,
             data.matrix,
             data.alpha,
-        );
+        )
+    }
+
+    /// Replay the buffered command list. Draws are stably sorted by pipeline
+    /// state so equal-key draws stay in submission order (preserving painter's
+    /// order for overlapping same-key sprites), then each contiguous run is
+    /// emitted with a single blend change.
+    pub fn flush_all(&mut self, gfx: &mut Graphics, projection: &Matrix4) -> Result<(), String> {
+        if self.commands.is_empty() {
+            return Ok(());
+        }
+
+        let commands = std::mem::take(&mut self.commands);
+        let blends: Vec<Option<BlendMode>> = commands.iter().map(|c| c.blend).collect();
+        let order = state_sorted_order(&blends);
+
+        for &i in &order {
+            let cmd = &commands[i];
+
+            // Flush on a blend change so each run draws under one state.
+            if self.pipeline.options.color_blend != cmd.blend {
+                self.flush(gfx, projection);
+                self.pipeline.options.color_blend = cmd.blend;
+            }
+
+            // A single buffered draw bigger than one batch can't fit even after
+            // a flush, so split it into batch-sized sub-draws.
+            if cmd.indices.len() > self.indices.len() {
+                self.split_and_flush(
+                    gfx,
+                    projection,
+                    &cmd.indices,
+                    &cmd.vertices,
+                    &cmd.color,
+                    &cmd.matrix,
+                    cmd.alpha,
+                )?;
+                continue;
+            }
+
+            // Flush when this draw would overflow the current batch.
+            if self.index + cmd.indices.len() >= self.indices.len() {
+                self.flush(gfx, projection);
+            }
+
+            self.push_vertices(
+                &cmd.indices,
+                &cmd.vertices,
+                &cmd.color,
+                &cmd.matrix,
+                cmd.alpha,
+            )?;
+        }
+
+        self.flush(gfx, projection);
+        Ok(())
     }
 
-    fn split_batch(&mut self, gfx: &mut Graphics, data: DrawData) {
-        // TODO this doesn't care about indices...
+    fn split_batch(&mut self, gfx: &mut Graphics, data: DrawData) -> Result<(), String> {
+        self.split_and_flush(
+            gfx,
+            data.projection,
+            data.indices,
+            data.vertices,
+            &data.color,
+            data.matrix,
+            data.alpha,
+        )
+    }
+
+    /// Split an over-large indexed draw into batch-sized sub-draws, flushing
+    /// each. Used both by `push_data` and, in deferred mode, by `flush_all` for
+    /// a single buffered draw bigger than one batch.
+    fn split_and_flush(
+        &mut self,
+        gfx: &mut Graphics,
+        projection: &Matrix4,
+        src_indices: &[u32],
+        src_vertices: &[f32],
+        color: &Color,
+        matrix: &Matrix4,
+        alpha: f32,
+    ) -> Result<(), String> {
+        // The position stride of the incoming vertices (x, y, z).
+        const OFFSET: usize = 3;
 
-        let mut indices = vec![0; self.indices.len()];
-        let iterations = (data.indices.len() / self.indices.len()) + 1;
+        // The indices must address vertices that actually exist before we start
+        // slicing, otherwise a malformed batch reads out of bounds.
+        validate_indices_in_bounds(src_indices, src_vertices.len(), OFFSET)?;
 
+        // Draw whatever is already pending so each sub-draw starts an empty batch.
+        self.flush(gfx, projection);
+
+        let chunk = self.indices.len();
+        let vertex_capacity = self.vertices.len() / self.vbos[0].offset();
+        let iterations = (src_indices.len() + chunk - 1) / chunk;
+
+        let mut remapped = vec![0; chunk];
         for i in 0..iterations {
-            let start = i * self.indices.len();
-            let end = (start + self.indices.len()).min(data.indices.len());
-            for (i, v) in (start..end).enumerate() {
-                indices[i] = (v - start) as u32;
+            let start = i * chunk;
+            let end = (start + chunk).min(src_indices.len());
+            let span = &src_indices[start..end];
+
+            // Slice by the vertex-id range this span references, not by its
+            // position in the index array, and remap against that base.
+            let (min, max) = validate_span_fits_capacity(span, vertex_capacity)?;
+
+            for (slot, index) in span.iter().enumerate() {
+                remapped[slot] = *index - min as u32;
             }
 
+            let v_start = min
+                .checked_mul(OFFSET)
+                .ok_or_else(|| "ColorBatcher -> vertex start overflow".to_string())?;
+            let v_end = (max + 1)
+                .checked_mul(OFFSET)
+                .ok_or_else(|| "ColorBatcher -> vertex end overflow".to_string())?;
+
             self.push_vertices(
-                &indices[0..end - start],
-                &data.vertices[start * 3..end * 3],
-                &data.color,
-                data.matrix,
-                data.alpha,
-            );
+                &remapped[0..span.len()],
+                &src_vertices[v_start..v_end],
+                color,
+                matrix,
+                alpha,
+            )?;
 
-            self.flush(gfx, data.projection);
+            self.flush(gfx, projection);
         }
+
+        Ok(())
     }
 
     fn push_vertices(
@@ -247,15 +723,24 @@ impl ColorBatcher {
         color: &Color,
         matrix: &Matrix4,
         alpha: f32,
-    ) {
-        for (i, index) in indices.iter().enumerate() {
-            self.indices[self.index + i] = self.index as u32 + *index;
+    ) -> Result<(), String> {
+        // Out of quad mode indices are written explicitly, remapped against the
+        // running vertex base. In quad mode the pre-built buffer already encodes
+        // them, so only the counts advance.
+        if !self.quad_mode {
+            for (i, index) in indices.iter().enumerate() {
+                self.indices[self.index + i] = self.vcount as u32 + *index;
+            }
         }
 
-        let offset = self.vbo.offset();
+        let offset = self.vbos[0].offset();
         let [r, g, b, a] = color.to_rgba();
-        let mut index_offset = self.index * offset;
+        let mut index_offset = self
+            .vcount
+            .checked_mul(offset)
+            .ok_or_else(|| "ColorBatcher -> vertex offset overflow".to_string())?;
 
+        let mut written = 0;
         for (i, _) in vertices.iter().enumerate().step_by(3) {
             let [x, y, z, _] = matrix4_mul_vector4(
                 matrix,
@@ -271,9 +756,12 @@ impl ColorBatcher {
             self.vertices[index_offset + 6] = a * alpha;
 
             index_offset += offset;
+            written += 1;
         }
 
+        self.vcount += written;
         self.index += indices.len();
+        Ok(())
     }
 
     pub fn flush(&mut self, gfx: &mut Graphics, projection: &Matrix4) {
@@ -281,11 +769,95 @@ impl ColorBatcher {
             return;
         }
 
+        let indices = if self.quad_mode {
+            &self.quad_indices
+        } else {
+            &self.indices
+        };
+
         gfx.set_pipeline(&self.pipeline);
-        gfx.bind_vertex_buffer(&self.vbo, &self.vertices);
-        gfx.bind_index_buffer(&self.ibo, &self.indices);
+        gfx.bind_vertex_buffer(&self.vbos[self.ring], &self.vertices);
+        gfx.bind_index_buffer(&self.ibos[self.ring], indices);
         gfx.bind_uniform(&self.matrix_loc, projection);
         gfx.draw(0, self.index as i32);
+
+        // Advance to the next ring slot for the following flush.
+        self.ring = (self.ring + 1) % self.vbos.len();
         self.index = 0;
+        self.vcount = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_indices_step_by_four_per_quad() {
+        let mut indices = vec![0u32; 12];
+        fill_quad_indices(&mut indices);
+        assert_eq!(indices, [0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+    }
+
+    #[test]
+    fn quad_indices_ignore_trailing_partial_quad() {
+        // chunks_exact_mut leaves a sub-6 tail untouched.
+        let mut indices = vec![9u32; 8];
+        fill_quad_indices(&mut indices);
+        assert_eq!(&indices[..6], &[0, 1, 2, 2, 3, 0]);
+        assert_eq!(&indices[6..], &[9, 9]);
+    }
+
+    #[test]
+    fn sorted_order_groups_equal_blends_keeping_submission_order() {
+        let a = Some(BlendMode::NORMAL);
+        let b = Some(BlendMode::ADD);
+        let blends = [a, b, a, b];
+        // First-appearance grouping: the two `a` draws, then the two `b` draws,
+        // each run in its original submission order.
+        assert_eq!(state_sorted_order(&blends), vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn sorted_order_is_stable_for_single_state() {
+        let blends = [None, None, None];
+        assert_eq!(state_sorted_order(&blends), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn rejects_indices_that_reference_more_vertices_than_provided() {
+        // Index 5 needs 6 vertices (* 3 floats each = 18), but only 3 were given.
+        let err = validate_indices_in_bounds(&[0, 1, 5], 9, 3).unwrap_err();
+        assert_eq!(
+            err,
+            "ColorBatcher -> indices reference 6 vertices but only 3 were provided"
+        );
+    }
+
+    #[test]
+    fn accepts_indices_that_fit_the_provided_vertices() {
+        assert!(validate_indices_in_bounds(&[0, 1, 2, 2, 3, 0], 12, 3).is_ok());
+    }
+
+    #[test]
+    fn rejects_vertex_count_overflow() {
+        // 2 vertices times a usize::MAX stride cannot be represented.
+        let err = validate_indices_in_bounds(&[1], usize::MAX, usize::MAX).unwrap_err();
+        assert_eq!(err, "ColorBatcher -> vertex count overflow");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_span_that_exceeds_destination_capacity() {
+        // The span spans vertex ids 10..=13, i.e. 4 vertices, but capacity is 2.
+        let err = validate_span_fits_capacity(&[10, 11, 12, 13], 2).unwrap_err();
+        assert_eq!(
+            err,
+            "ColorBatcher -> sub-batch references 4 vertices, exceeds capacity 2"
+        );
+    }
+
+    #[test]
+    fn accepts_span_that_fits_destination_capacity() {
+        assert_eq!(validate_span_fits_capacity(&[4, 5, 6, 7], 4), Ok((4, 7)));
+    }
+}